@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+pub enum Outcome {
+    Processed(PathBuf),
+    Skipped,
+    Error(anyhow::Error),
+}
+
+/// Expands each argument as a glob pattern, falling back to the literal path
+/// when the pattern has no wildcard matches. This lets a plain, possibly
+/// missing path surface its own "file not found" error later during
+/// processing instead of silently vanishing here. Duplicate paths (e.g. two
+/// overlapping patterns matching the same file) are dropped, since each
+/// resolved input's processed output would otherwise collide.
+pub fn expand_inputs(patterns: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        match glob::glob(pattern) {
+            Ok(paths) => {
+                let matched: Vec<PathBuf> = paths.filter_map(std::result::Result::ok).collect();
+                if matched.is_empty() {
+                    files.push(PathBuf::from(pattern));
+                } else {
+                    files.extend(matched);
+                }
+            }
+            Err(_) => files.push(PathBuf::from(pattern)),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    files
+}
+
+/// Runs `process` over every file in `files`, optionally spreading the work
+/// across a `jobs`-sized thread pool.
+pub fn run<F>(files: &[PathBuf], jobs: usize, process: F) -> Result<Vec<Outcome>>
+where
+    F: Fn(&PathBuf) -> Result<Option<PathBuf>> + Sync,
+{
+    let pool = ThreadPoolBuilder::new().num_threads(jobs.max(1)).build()?;
+    let outcomes = pool.install(|| {
+        files
+            .par_iter()
+            .map(|file| match process(file) {
+                Ok(Some(out)) => Outcome::Processed(out),
+                Ok(None) => Outcome::Skipped,
+                Err(e) => Outcome::Error(e),
+            })
+            .collect()
+    });
+    Ok(outcomes)
+}
+
+/// Prints a processed/skipped/error summary for the batch. Returns `true`
+/// unless every file in the batch errored.
+pub fn report(files: &[PathBuf], outcomes: &[Outcome]) -> bool {
+    let mut processed = 0;
+    let mut skipped = 0;
+    let mut errored = 0;
+    for (file, outcome) in files.iter().zip(outcomes) {
+        match outcome {
+            Outcome::Processed(out) => {
+                processed += 1;
+                println!("[成功] {} -> {}", file.display(), out.display());
+            }
+            Outcome::Skipped => {
+                skipped += 1;
+                println!("[跳过] {}", file.display());
+            }
+            Outcome::Error(e) => {
+                errored += 1;
+                eprintln!("[失败] {}: {e:#}", file.display());
+            }
+        }
+    }
+    println!(
+        "处理完成：成功 {processed}，跳过 {skipped}，失败 {errored}（共 {} 个文件）",
+        files.len()
+    );
+    errored < files.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_inputs_keeps_literal_paths_with_no_glob_matches() {
+        let files = expand_inputs(&["no/such/dir/missing.xlsx".to_string()]);
+        assert_eq!(files, vec![PathBuf::from("no/such/dir/missing.xlsx")]);
+    }
+
+    #[test]
+    fn expand_inputs_dedupes_the_same_path_from_overlapping_patterns() {
+        let files = expand_inputs(&[
+            "same/path.xlsx".to_string(),
+            "same/path.xlsx".to_string(),
+        ]);
+        assert_eq!(files, vec![PathBuf::from("same/path.xlsx")]);
+    }
+
+    #[test]
+    fn expand_inputs_keeps_same_basename_from_different_directories() {
+        // Regression: these must NOT be deduped (dirA/data.xlsx and
+        // dirB/data.xlsx are different inputs that happen to share a name).
+        let files = expand_inputs(&[
+            "dirA/data.xlsx".to_string(),
+            "dirB/data.xlsx".to_string(),
+        ]);
+        assert_eq!(
+            files,
+            vec![PathBuf::from("dirA/data.xlsx"), PathBuf::from("dirB/data.xlsx")]
+        );
+    }
+
+    #[test]
+    fn report_false_only_when_every_file_errored() {
+        let files = vec![PathBuf::from("a.xlsx")];
+        let outcomes = vec![Outcome::Error(anyhow::anyhow!("boom"))];
+        assert!(!report(&files, &outcomes));
+
+        let files = vec![PathBuf::from("a.xlsx"), PathBuf::from("b.xlsx")];
+        let outcomes = vec![
+            Outcome::Error(anyhow::anyhow!("boom")),
+            Outcome::Processed(PathBuf::from("processed_b.xlsx")),
+        ];
+        assert!(report(&files, &outcomes));
+    }
+
+    #[test]
+    fn report_true_for_skipped_files() {
+        let files = vec![PathBuf::from("a.xlsx")];
+        let outcomes = vec![Outcome::Skipped];
+        assert!(report(&files, &outcomes));
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::Write;
+
+use anyhow::Result;
+use calamine::{Data, Range};
+
+use crate::datatype_to_string;
+
+/// Serializes `range` as delimiter-separated values, quoting fields per
+/// RFC 4180 (quotes around any field containing the delimiter, a quote, or a
+/// newline; embedded quotes doubled).
+pub fn write_csv<W: Write>(
+    range: &Range<Data>,
+    delimiter: char,
+    date_format: &str,
+    mut writer: W,
+) -> Result<()> {
+    let (height, width) = range.get_size();
+    for row0 in 0..height {
+        let mut fields = Vec::with_capacity(width);
+        for col0 in 0..width {
+            let value = range
+                .get_value((row0 as u32, col0 as u32))
+                .map(|v| datatype_to_string(v, date_format))
+                .unwrap_or_default();
+            fields.push(quote_field(&value, delimiter));
+        }
+        writeln!(writer, "{}", fields.join(&delimiter.to_string()))?;
+    }
+    Ok(())
+}
+
+fn quote_field(field: &str, delimiter: char) -> String {
+    let needs_quoting = field.contains(delimiter)
+        || field.contains('"')
+        || field.contains('\n')
+        || field.contains('\r');
+    if needs_quoting {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Derives the `[cols="..."]` weights for an AsciiDoc table: a column's
+/// explicit width (Excel's "characters" unit) is scaled ×100 and rounded, with
+/// a floor of 1; columns without an explicit width get weight 1. Those columns
+/// split the table equally only when no column has an explicit width; next to
+/// ×100-scaled weights a weight-1 column is rendered close to zero width.
+fn column_weights(column_widths: &[Option<f64>]) -> Vec<u32> {
+    column_widths
+        .iter()
+        .map(|width| match width {
+            Some(w) => (w * 100.0).round().max(1.0) as u32,
+            None => 1,
+        })
+        .collect()
+}
+
+/// Escapes a cell value for AsciiDoc's default psv table format, where a
+/// literal `|` starts a new cell — an unescaped one would shift every
+/// following cell in the row.
+fn escape_adoc_cell(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Serializes `range` as an AsciiDoc table: a `[cols="..."]` header derived
+/// from `column_widths`, followed by `|===`, one `|cell` per value in
+/// row-major order, and a closing `|===`.
+pub fn write_adoc<W: Write>(
+    range: &Range<Data>,
+    column_widths: &[Option<f64>],
+    date_format: &str,
+    mut writer: W,
+) -> Result<()> {
+    let (height, width) = range.get_size();
+    let weights = column_weights(column_widths);
+    let cols = weights
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "[cols=\"{cols}\"]")?;
+    writeln!(writer, "|===")?;
+    for row0 in 0..height {
+        for col0 in 0..width {
+            let value = range
+                .get_value((row0 as u32, col0 as u32))
+                .map(|v| datatype_to_string(v, date_format))
+                .unwrap_or_default();
+            writeln!(writer, "|{}", escape_adoc_cell(&value))?;
+        }
+    }
+    writeln!(writer, "|===")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_field_only_when_needed() {
+        assert_eq!(quote_field("plain", ','), "plain");
+        assert_eq!(quote_field("a,b", ','), "\"a,b\"");
+        assert_eq!(quote_field("a\"b", ','), "\"a\"\"b\"");
+        assert_eq!(quote_field("a\nb", ','), "\"a\nb\"");
+    }
+
+    #[test]
+    fn column_weights_scales_explicit_widths_and_defaults_others() {
+        assert_eq!(column_weights(&[Some(8.43), None, Some(0.0)]), vec![843, 1, 1]);
+    }
+
+    #[test]
+    fn escape_adoc_cell_escapes_pipes_and_backslashes() {
+        assert_eq!(escape_adoc_cell("a|b"), "a\\|b");
+        assert_eq!(escape_adoc_cell("a\\b"), "a\\\\b");
+        assert_eq!(escape_adoc_cell("plain"), "plain");
+    }
+}
@@ -1,25 +1,134 @@
+mod batch;
+mod export;
+mod rules;
+mod sheets;
+
+use std::fs::File;
+use std::io;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use calamine::{open_workbook_auto, Data, Reader};
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use clap::Parser;
 use regex::Regex;
 
-fn cell_ref(col_1_based: usize, row_1_based: usize) -> String {
-    fn col_to_name(mut col: usize) -> String {
-        // 1 -> A, 26 -> Z, 27 -> AA
-        let mut name = String::new();
-        while col > 0 {
-            let rem = (col - 1) % 26;
-            name.push((b'A' + rem as u8) as char);
-            col = (col - 1) / 26;
+use rules::{Action, Rule};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "清洗实验室仪器导出的 Excel 报表")]
+struct Cli {
+    /// 待处理的 Excel 文件，支持多个路径和 glob 模式（如 `data/*.xlsx`）
+    inputs: Vec<String>,
+
+    /// 规则配置文件（TOML），未提供时使用内置的离子色谱规则
+    #[arg(long)]
+    rules: Option<PathBuf>,
+
+    /// 将当前工作表导出为 CSV/TSV，路径为 `-` 时输出到标准输出（仅对第一个文件生效）
+    #[arg(long, value_name = "PATH")]
+    to_csv: Option<PathBuf>,
+
+    /// --to-csv 使用的分隔符
+    #[arg(long, default_value = ",")]
+    delimiter: char,
+
+    /// 日期/时间单元格使用的 chrono 格式字符串
+    #[arg(long, default_value = DEFAULT_DATE_FORMAT)]
+    date_format: String,
+
+    /// 并行处理的文件数
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+
+    /// 未指定 --rules 时，内置规则对匹配单元格采取的动作
+    #[arg(long, value_enum, default_value_t = Mode::Clear)]
+    mode: Mode,
+
+    /// highlight/both 模式使用的背景色（十六进制 RRGGBB）
+    #[arg(long, default_value = "FFFF00")]
+    highlight_color: String,
+
+    /// 要处理的工作表：`all`、单个表名，或逗号分隔的索引列表（从 1 开始）；
+    /// 默认只处理当前激活的工作表
+    #[arg(long)]
+    sheets: Option<String>,
+
+    /// 将当前工作表导出为 AsciiDoc 表格，路径为 `-` 时输出到标准输出（仅对第一个文件生效）
+    #[arg(long, value_name = "PATH")]
+    to_adoc: Option<PathBuf>,
+}
+
+/// Default ISO-8601-ish format applied to `Data::DateTime` cells.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// `--mode` only ever builds the built-in rule, which has no TOML `set_value`
+/// to draw on, so it intentionally exposes a subset of `rules::Action`
+/// (no `set-value`) rather than the full action type.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+#[clap(rename_all = "kebab-case")]
+enum Mode {
+    Clear,
+    Highlight,
+    Both,
+}
+
+impl From<Mode> for Action {
+    fn from(mode: Mode) -> Action {
+        match mode {
+            Mode::Clear => Action::Clear,
+            Mode::Highlight => Action::Highlight,
+            Mode::Both => Action::Both,
         }
-        name.chars().rev().collect()
     }
+}
 
+fn col_to_name(mut col: usize) -> String {
+    // 1 -> A, 26 -> Z, 27 -> AA
+    let mut name = String::new();
+    while col > 0 {
+        let rem = (col - 1) % 26;
+        name.push((b'A' + rem as u8) as char);
+        col = (col - 1) / 26;
+    }
+    name.chars().rev().collect()
+}
+
+fn cell_ref(col_1_based: usize, row_1_based: usize) -> String {
     format!("{}{}", col_to_name(col_1_based), row_1_based)
 }
 
-fn datatype_to_string(v: &Data) -> String {
+/// Parses an Excel-style cell reference like `A3` into 0-based `(row, col)`.
+fn parse_cell_ref(reference: &str) -> Option<(u32, u32)> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() || digits.is_empty() {
+        return None;
+    }
+
+    let mut col: u32 = 0;
+    for c in letters.chars() {
+        if !c.is_ascii_alphabetic() {
+            return None;
+        }
+        col = col * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1);
+    }
+    let row: u32 = digits.parse().ok()?;
+    Some((row.checked_sub(1)?, col.checked_sub(1)?))
+}
+
+/// Renders `dt` with `date_format`, or `None` if the format string contains a
+/// specifier chrono can't satisfy (e.g. `%Z` on a naive datetime, or an
+/// unknown specifier like `%Q`) — `DelayedFormat`'s `Display` impl returns an
+/// error in that case, and a bare `.to_string()` would panic rather than let
+/// a user-supplied `--date-format` fail gracefully.
+fn format_datetime(dt: chrono::NaiveDateTime, date_format: &str) -> Option<String> {
+    use std::fmt::Write;
+    let mut formatted = String::new();
+    write!(formatted, "{}", dt.format(date_format)).ok()?;
+    Some(formatted)
+}
+
+pub(crate) fn datatype_to_string(v: &Data, date_format: &str) -> String {
     match v {
         Data::Empty => String::new(),
         Data::String(s) => s.clone(),
@@ -32,84 +141,190 @@ fn datatype_to_string(v: &Data) -> String {
         }
         Data::Int(i) => i.to_string(),
         Data::Bool(b) => b.to_string(),
-        Data::DateTime(f) => f.to_string(),
+        Data::DateTime(dt) => dt
+            .as_datetime()
+            .and_then(|dt| format_datetime(dt, date_format))
+            .unwrap_or_else(|| dt.as_f64().to_string()),
         Data::DateTimeIso(s) => s.clone(),
         Data::DurationIso(s) => s.clone(),
         Data::Error(e) => format!("{e:?}"),
     }
 }
 
+/// Derives the output path for a processed workbook, writing it alongside
+/// `input` (in `input`'s own directory) rather than into the current
+/// directory — otherwise two inputs with the same file name from different
+/// directories (e.g. `dtproton *.xlsx data/*.xlsx`) would collide on the same
+/// `processed_<name>.xlsx` and silently clobber each other's output.
 fn processed_output_path(input: &Path) -> PathBuf {
     let file_name = input
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| "output.xlsx".to_string());
-    PathBuf::from(format!("processed_{file_name}"))
+    input.with_file_name(format!("processed_{file_name}"))
 }
 
-fn process_excel(path: &Path) -> Result<Option<PathBuf>> {
-    // Use umya-spreadsheet to determine the active sheet (to match excelize behavior),
-    // and later to write the modified workbook back out.
-    let mut book = umya_spreadsheet::reader::xlsx::read(path)
-        .with_context(|| format!("无法读取文件: {}", path.display()))?;
-    let active_sheet_index = *book.get_workbook_view().get_active_tab() as usize;
-
-    let mut workbook = open_workbook_auto(path).with_context(|| format!("无法打开文件: {}", path.display()))?;
-    let sheet_names = workbook.sheet_names();
-    let sheet_name = sheet_names
-        .get(active_sheet_index)
-        .or_else(|| sheet_names.get(0))
-        .cloned()
-        .ok_or_else(|| anyhow!("工作簿中没有工作表"))?;
-
-    let range = workbook
-        .worksheet_range(&sheet_name)
-        .with_context(|| format!("无法读取工作表: {sheet_name}"))?;
-
-    // A3 -> row=3, col=A => (2,0) in 0-based
-    let a3 = range
-        .get_value((2u32, 0u32))
-        .map(datatype_to_string)
+/// Returns `true` if `rule`'s guard (if any) holds against `range`.
+fn guard_holds(rule: &Rule, range: &Range<Data>, date_format: &str) -> bool {
+    let Some(guard) = &rule.guard else {
+        return true;
+    };
+    let Some((row, col)) = parse_cell_ref(&guard.cell) else {
+        return false;
+    };
+    let value = range
+        .get_value((row, col))
+        .map(|v| datatype_to_string(v, date_format))
         .unwrap_or_default();
+    value.trim() == guard.equals
+}
 
-    if a3.trim() != "离子色谱" {
-        println!("A3 单元格不是“离子色谱”，无需处理。");
-        return Ok(None);
+/// Sets an ARGB background fill (the alpha channel is forced to opaque) on
+/// the cell at `addr`.
+fn highlight_cell(sheet: &mut umya_spreadsheet::Worksheet, addr: &str, color_hex: &str) {
+    let argb = format!("FF{}", color_hex.trim_start_matches('#').to_uppercase());
+    sheet.get_style_mut(addr).set_background_color(argb);
+}
+
+/// Applies `rule` to `sheet`, using `range` (read via calamine for the same
+/// worksheet) to decide which cells match.
+fn apply_rule(
+    sheet: &mut umya_spreadsheet::Worksheet,
+    range: &Range<Data>,
+    rule: &Rule,
+    date_format: &str,
+    highlight_color: &str,
+) -> Result<usize> {
+    if !guard_holds(rule, range, date_format) {
+        return Ok(0);
     }
 
     let (height, width) = range.get_size();
-    if height < 6 {
-        println!("表格行数不足6行，无需处理第6行及以后的数据。");
-        return Ok(None);
+    let start_row0 = rule.start_row.saturating_sub(1) as usize;
+    if height <= start_row0 {
+        return Ok(0);
     }
 
-    let re = Regex::new(r"\((RM|C)\)").expect("valid regex");
+    let re = Regex::new(&rule.match_regex)
+        .with_context(|| format!("规则中的正则表达式无效: {}", rule.match_regex))?;
 
-    // Collect cells to clear (1-based coordinates for Excel refs)
-    let mut to_clear: Vec<String> = Vec::new();
-    for row0 in 5..height {
+    let mut matched: Vec<String> = Vec::new();
+    for row0 in start_row0..height {
         for col0 in 0..width {
+            if let Some(columns) = &rule.column_filter {
+                if !columns.contains(&(col0 as u32 + 1)) {
+                    continue;
+                }
+            }
             let value = range
                 .get_value((row0 as u32, col0 as u32))
-                .map(datatype_to_string)
+                .map(|v| datatype_to_string(v, date_format))
                 .unwrap_or_default();
             if !value.is_empty() && re.is_match(&value) {
-                to_clear.push(cell_ref(col0 + 1, row0 + 1));
+                matched.push(cell_ref(col0 + 1, row0 + 1));
             }
         }
     }
 
-    if to_clear.is_empty() {
-        // Still mimic Go behavior: save only if changes? In Go it always SaveAs.
-        // We'll still save a copy so behavior matches "processed_" output.
-        // (If you prefer skipping when no changes, tell me.)
+    let count = matched.len();
+    for addr in matched {
+        match rule.action {
+            Action::Clear => {
+                sheet.get_cell_mut(addr.as_str()).set_value("");
+            }
+            Action::SetValue => {
+                let value = rule.set_value.as_deref().unwrap_or_default();
+                sheet.get_cell_mut(addr.as_str()).set_value(value);
+            }
+            Action::Highlight => highlight_cell(sheet, addr.as_str(), highlight_color),
+            Action::Both => {
+                sheet.get_cell_mut(addr.as_str()).set_value("");
+                highlight_cell(sheet, addr.as_str(), highlight_color);
+            }
+        }
     }
 
-    // calamine is read-only; use umya-spreadsheet to write the updated workbook.
-    let sheet = book.get_active_sheet_mut();
+    Ok(count)
+}
+
+/// Opens `path` and returns the `Range<Data>` for its active sheet (the tab
+/// umya-spreadsheet reports as active, matching excelize behavior).
+fn active_sheet_range(path: &Path) -> Result<Range<Data>> {
+    let book = umya_spreadsheet::reader::xlsx::lazy_read(path)
+        .with_context(|| format!("无法读取文件: {}", path.display()))?;
+    let active_sheet_index = *book.get_workbook_view().get_active_tab() as usize;
+
+    let mut workbook = open_workbook_auto(path).with_context(|| format!("无法打开文件: {}", path.display()))?;
+    let sheet_names = workbook.sheet_names();
+    let sheet_name = sheet_names
+        .get(active_sheet_index)
+        .or_else(|| sheet_names.first())
+        .cloned()
+        .ok_or_else(|| anyhow!("工作簿中没有工作表"))?;
 
-    for addr in to_clear {
-        sheet.get_cell_mut(addr.as_str()).set_value("");
+    workbook
+        .worksheet_range(&sheet_name)
+        .with_context(|| format!("无法读取工作表: {sheet_name}"))
+}
+
+/// Reads the explicit column width (in Excel's "characters" unit) set for
+/// each column of `path`'s active sheet, in column order. `None` means the
+/// column has no explicit width.
+fn active_sheet_column_widths(path: &Path, width: usize) -> Result<Vec<Option<f64>>> {
+    // A full read: `get_sheet` does not deserialize a lazily read worksheet,
+    // so its column dimensions would all come back empty.
+    let book = umya_spreadsheet::reader::xlsx::read(path)
+        .with_context(|| format!("无法读取文件: {}", path.display()))?;
+    let active_sheet_index = *book.get_workbook_view().get_active_tab() as usize;
+    let sheet = book
+        .get_sheet(&active_sheet_index)
+        .ok_or_else(|| anyhow!("工作簿中没有工作表"))?;
+
+    Ok((0..width)
+        .map(|col0| {
+            let col_name = col_to_name(col0 + 1);
+            sheet
+                .get_column_dimension(&col_name)
+                .map(|dim| *dim.get_width())
+        })
+        .collect())
+}
+
+fn process_excel(
+    path: &Path,
+    rules: &[Rule],
+    date_format: &str,
+    highlight_color: &str,
+    sheet_selection: &sheets::Selection,
+) -> Result<Option<PathBuf>> {
+    // lazy_read only materializes the sheets we actually touch below, which
+    // matters for workbooks with many large sheets.
+    let mut book = umya_spreadsheet::reader::xlsx::lazy_read(path)
+        .with_context(|| format!("无法读取文件: {}", path.display()))?;
+    let active_sheet_index = *book.get_workbook_view().get_active_tab() as usize;
+
+    let mut workbook = open_workbook_auto(path).with_context(|| format!("无法打开文件: {}", path.display()))?;
+    let sheet_names = workbook.sheet_names();
+    let targets = sheets::resolve(sheet_selection, &sheet_names, active_sheet_index)?;
+    if targets.is_empty() {
+        return Err(anyhow!("工作簿中没有工作表"));
+    }
+
+    let mut total_matches = 0usize;
+    for name in &targets {
+        let range = workbook
+            .worksheet_range(name)
+            .with_context(|| format!("无法读取工作表: {name}"))?;
+        let sheet = book
+            .get_sheet_by_name_mut(name)
+            .ok_or_else(|| anyhow!("工作簿中没有工作表: {name}"))?;
+        for rule in rules {
+            total_matches += apply_rule(sheet, &range, rule, date_format, highlight_color)?;
+        }
+    }
+
+    if total_matches == 0 {
+        return Ok(None);
     }
 
     let output_path = processed_output_path(path);
@@ -127,17 +342,210 @@ fn main() {
 }
 
 fn real_main() -> Result<()> {
-    let mut args = std::env::args_os();
-    let _exe = args.next();
-    let Some(input) = args.next() else {
-        println!("请提供文件名作为参数，例如：dtproton 45vocs2.xlsx");
+    let cli = Cli::parse();
+
+    let files = batch::expand_inputs(&cli.inputs);
+    if files.is_empty() {
+        println!("请提供文件名作为参数，例如：dtproton 45vocs2.xlsx 或 data/*.xlsx");
         return Ok(());
+    }
+
+    let rules = match &cli.rules {
+        Some(path) => rules::load_rules(path)?,
+        None => rules::default_rules(cli.mode.into()),
     };
 
-    let input_path = PathBuf::from(input);
-    let out = process_excel(&input_path)?;
-    if let Some(out) = out {
-        println!("文件已处理并保存为: {}", out.display());
+    let sheet_selection = match &cli.sheets {
+        Some(spec) => sheets::parse(spec),
+        None => sheets::Selection::Active,
+    };
+
+    let outcomes = batch::run(&files, cli.jobs, |path| {
+        process_excel(
+            path,
+            &rules,
+            &cli.date_format,
+            &cli.highlight_color,
+            &sheet_selection,
+        )
+    })?;
+
+    // --to-csv/--to-adoc read the file that process_excel actually produced
+    // (or, if nothing matched, the untouched original), so they export the
+    // cleaned data rather than a second, pre-rule read of the input.
+    if cli.to_csv.is_some() || cli.to_adoc.is_some() {
+        if files.len() > 1 {
+            eprintln!(
+                "--to-csv/--to-adoc 仅支持单个文件，将只导出: {}",
+                files[0].display()
+            );
+        }
+        let exported_path = match &outcomes[0] {
+            batch::Outcome::Processed(out) => out.clone(),
+            batch::Outcome::Skipped => files[0].clone(),
+            batch::Outcome::Error(e) => return Err(anyhow!("{e:#}")),
+        };
+
+        if let Some(csv_path) = &cli.to_csv {
+            let range = active_sheet_range(&exported_path)?;
+            if csv_path.as_os_str() == "-" {
+                export::write_csv(&range, cli.delimiter, &cli.date_format, io::stdout().lock())?;
+            } else {
+                let file = File::create(csv_path)
+                    .with_context(|| format!("无法创建文件: {}", csv_path.display()))?;
+                export::write_csv(&range, cli.delimiter, &cli.date_format, file)?;
+                println!("已导出为: {}", csv_path.display());
+            }
+        }
+
+        if let Some(adoc_path) = &cli.to_adoc {
+            let range = active_sheet_range(&exported_path)?;
+            let (_, width) = range.get_size();
+            let column_widths = active_sheet_column_widths(&exported_path, width)?;
+            if adoc_path.as_os_str() == "-" {
+                export::write_adoc(&range, &column_widths, &cli.date_format, io::stdout().lock())?;
+            } else {
+                let file = File::create(adoc_path)
+                    .with_context(|| format!("无法创建文件: {}", adoc_path.display()))?;
+                export::write_adoc(&range, &column_widths, &cli.date_format, file)?;
+                println!("已导出为: {}", adoc_path.display());
+            }
+        }
+    }
+
+    if files.len() == 1 {
+        match &outcomes[0] {
+            batch::Outcome::Processed(out) => println!("文件已处理并保存为: {}", out.display()),
+            batch::Outcome::Skipped => println!("没有符合规则的单元格，无需处理。"),
+            batch::Outcome::Error(e) => return Err(anyhow!("{e:#}")),
+        }
+        return Ok(());
+    }
+
+    if !batch::report(&files, &outcomes) {
+        std::process::exit(1);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use calamine::Cell;
+
+    use super::*;
+    use rules::Guard;
+
+    #[test]
+    fn format_datetime_formats_valid_specifiers() {
+        let dt = chrono::NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 4, 5)
+            .unwrap();
+        assert_eq!(
+            format_datetime(dt, DEFAULT_DATE_FORMAT),
+            Some("2024-01-02T03:04:05".to_string())
+        );
+    }
+
+    #[test]
+    fn format_datetime_returns_none_instead_of_panicking_on_bad_specifiers() {
+        let dt = chrono::NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 4, 5)
+            .unwrap();
+        // %Z: timezone specifier, unsatisfiable for a naive datetime.
+        assert_eq!(format_datetime(dt, "%Y-%m-%d %Z"), None);
+        // %Q: not a real chrono specifier.
+        assert_eq!(format_datetime(dt, "%Q"), None);
+    }
+
+    /// Builds a `Range` covering every row in `rows` from (0, 0), so it
+    /// behaves like a real worksheet range even when early rows are blank.
+    fn range_from_rows(rows: &[&[&str]]) -> Range<Data> {
+        let width = rows.iter().map(|cols| cols.len()).max().unwrap_or(0).max(1);
+        let cells = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cols)| {
+                (0..width).map(move |col| {
+                    let value = cols.get(col).map(|v| v.to_string()).unwrap_or_default();
+                    Cell::new((row as u32, col as u32), Data::String(value))
+                })
+            })
+            .collect();
+        Range::from_sparse(cells)
+    }
+
+    fn sample_rule(action: Action) -> Rule {
+        Rule {
+            guard: Some(Guard {
+                cell: "A3".to_string(),
+                equals: "离子色谱".to_string(),
+            }),
+            start_row: 6,
+            column_filter: None,
+            match_regex: r"\((RM|C)\)".to_string(),
+            action,
+            set_value: None,
+        }
+    }
+
+    #[test]
+    fn guard_holds_compares_trimmed_cell_value() {
+        let rule = sample_rule(Action::Clear);
+        let range = range_from_rows(&[&[], &[], &["离子色谱 "]]);
+        assert!(guard_holds(&rule, &range, DEFAULT_DATE_FORMAT));
+    }
+
+    #[test]
+    fn guard_holds_false_when_guard_value_differs() {
+        let rule = sample_rule(Action::Clear);
+        let range = range_from_rows(&[&[], &[], &["别的仪器"]]);
+        assert!(!guard_holds(&rule, &range, DEFAULT_DATE_FORMAT));
+    }
+
+    #[test]
+    fn guard_holds_true_without_a_guard() {
+        let mut rule = sample_rule(Action::Clear);
+        rule.guard = None;
+        let range = range_from_rows(&[]);
+        assert!(guard_holds(&rule, &range, DEFAULT_DATE_FORMAT));
+    }
+
+    #[test]
+    fn apply_rule_clears_only_matching_cells_from_start_row() {
+        let rule = sample_rule(Action::Clear);
+        let range = range_from_rows(&[
+            &[],
+            &[],
+            &["离子色谱"],
+            &[],
+            &[],
+            &["1.0(RM)", "keep"],
+        ]);
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_sheet_mut(&0).unwrap();
+        sheet.get_cell_mut("A6").set_value("1.0(RM)");
+        sheet.get_cell_mut("B6").set_value("keep");
+
+        let matched = apply_rule(sheet, &range, &rule, DEFAULT_DATE_FORMAT, "FFFF00").unwrap();
+
+        assert_eq!(matched, 1);
+        assert_eq!(sheet.get_value("A6"), "");
+        assert_eq!(sheet.get_value("B6"), "keep");
+    }
+
+    #[test]
+    fn apply_rule_is_a_no_op_when_guard_fails() {
+        let rule = sample_rule(Action::Clear);
+        let range = range_from_rows(&[&[], &[], &["别的仪器"], &[], &[], &["1.0(RM)"]]);
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_sheet_mut(&0).unwrap();
+        sheet.get_cell_mut("A6").set_value("1.0(RM)");
+
+        let matched = apply_rule(sheet, &range, &rule, DEFAULT_DATE_FORMAT, "FFFF00").unwrap();
+
+        assert_eq!(matched, 0);
+        assert_eq!(sheet.get_value("A6"), "1.0(RM)");
+    }
+}
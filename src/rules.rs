@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A guard is an optional precondition cell that must equal a given value
+/// before a rule's action is applied to the sheet (e.g. `A3 == "离子色谱"`).
+#[derive(Debug, Deserialize)]
+pub struct Guard {
+    pub cell: String,
+    pub equals: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Clear,
+    SetValue,
+    Highlight,
+    /// Clears the value but still colors the cell.
+    Both,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    pub guard: Option<Guard>,
+    pub start_row: u32,
+    /// 1-based column numbers to restrict matching to; `None` means all columns.
+    pub column_filter: Option<Vec<u32>>,
+    pub match_regex: String,
+    pub action: Action,
+    /// Value to write when `action = "set_value"`.
+    #[serde(default)]
+    pub set_value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+/// Loads rules from a TOML file shaped as a list of `[[rule]]` tables.
+pub fn load_rules(path: &Path) -> Result<Vec<Rule>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取规则文件: {}", path.display()))?;
+    let parsed: RuleFile = toml::from_str(&text)
+        .with_context(|| format!("无法解析规则文件: {}", path.display()))?;
+    Ok(parsed.rule)
+}
+
+/// The built-in single rule that reproduces the tool's original hard-coded
+/// 离子色谱 behavior, used when no `--rules` file is given. `action` comes
+/// from `--mode` (clear/highlight/both) so the built-in rule can clear,
+/// highlight, or both; `--mode` never produces `Action::SetValue`, since
+/// that action needs a `set_value` only a `--rules` file can supply.
+pub fn default_rules(action: Action) -> Vec<Rule> {
+    vec![Rule {
+        guard: Some(Guard {
+            cell: "A3".to_string(),
+            equals: "离子色谱".to_string(),
+        }),
+        start_row: 6,
+        column_filter: None,
+        match_regex: r"\((RM|C)\)".to_string(),
+        action,
+        set_value: None,
+    }]
+}
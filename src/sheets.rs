@@ -0,0 +1,117 @@
+use anyhow::{bail, Result};
+
+/// Which worksheets a run should touch, as selected by `--sheets`.
+pub enum Selection {
+    /// The tab umya-spreadsheet reports as active (the original, pre-`--sheets`
+    /// behavior).
+    Active,
+    All,
+    /// Raw, comma-separated `--sheets` tokens, each either a sheet name or a
+    /// 1-based index — resolved against the workbook's actual sheet names in
+    /// `resolve`, since only there do we know which names exist (and so
+    /// whether a numeric-looking token is itself a sheet name).
+    Tokens(Vec<String>),
+}
+
+/// Parses a `--sheets` value: `all`, or a comma-separated list of sheet
+/// names and/or 1-based indices.
+pub fn parse(spec: &str) -> Selection {
+    if spec.eq_ignore_ascii_case("all") {
+        return Selection::All;
+    }
+
+    Selection::Tokens(spec.split(',').map(|s| s.trim().to_string()).collect())
+}
+
+/// Resolves a selection against the workbook's actual sheet names. `Active`
+/// and `All` yield names in workbook order; `Tokens` yields them in the order
+/// the tokens were given. Each token is tried as an exact sheet name first (so a
+/// sheet literally named e.g. `2024` can always be selected by name) and
+/// only falls back to a 1-based index if no such sheet exists. Errors if any
+/// token matches neither.
+pub fn resolve(selection: &Selection, sheet_names: &[String], active_index: usize) -> Result<Vec<String>> {
+    match selection {
+        Selection::Active => Ok(sheet_names
+            .get(active_index)
+            .or_else(|| sheet_names.first())
+            .cloned()
+            .into_iter()
+            .collect()),
+        Selection::All => Ok(sheet_names.to_vec()),
+        Selection::Tokens(tokens) => {
+            let mut resolved = Vec::with_capacity(tokens.len());
+            let mut unmatched = Vec::new();
+            for token in tokens {
+                if let Some(name) = sheet_names.iter().find(|name| *name == token) {
+                    resolved.push(name.clone());
+                    continue;
+                }
+                match token.parse::<usize>().ok().and_then(|i| i.checked_sub(1)) {
+                    Some(index) if sheet_names.get(index).is_some() => {
+                        resolved.push(sheet_names[index].clone());
+                    }
+                    _ => unmatched.push(token.clone()),
+                }
+            }
+            if !unmatched.is_empty() {
+                bail!(
+                    "--sheets 中未找到以下工作表（按名称或从 1 开始的索引匹配）: {}",
+                    unmatched.join(", ")
+                );
+            }
+            Ok(resolved)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_all_is_case_insensitive() {
+        assert!(matches!(parse("ALL"), Selection::All));
+    }
+
+    #[test]
+    fn parse_splits_on_comma_and_trims() {
+        match parse(" Sheet1 , 2 ") {
+            Selection::Tokens(tokens) => assert_eq!(tokens, vec!["Sheet1", "2"]),
+            _ => panic!("expected Tokens"),
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_exact_name_match_over_index() {
+        // A sheet literally named "2" should win over "the 2nd sheet".
+        let sheet_names = names(&["2", "other"]);
+        let selection = Selection::Tokens(vec!["2".to_string()]);
+        assert_eq!(resolve(&selection, &sheet_names, 0).unwrap(), vec!["2"]);
+    }
+
+    #[test]
+    fn resolve_falls_back_to_one_based_index() {
+        let sheet_names = names(&["a", "b", "c"]);
+        let selection = Selection::Tokens(vec!["2".to_string()]);
+        assert_eq!(resolve(&selection, &sheet_names, 0).unwrap(), vec!["b"]);
+    }
+
+    #[test]
+    fn resolve_reports_unmatched_tokens_instead_of_dropping_them() {
+        let sheet_names = names(&["a", "b"]);
+        let selection = Selection::Tokens(vec!["a".to_string(), "nope".to_string(), "9".to_string()]);
+        let err = resolve(&selection, &sheet_names, 0).unwrap_err();
+        assert!(err.to_string().contains("nope"));
+        assert!(err.to_string().contains('9'));
+    }
+
+    #[test]
+    fn resolve_all_returns_every_sheet_in_order() {
+        let sheet_names = names(&["a", "b"]);
+        assert_eq!(resolve(&Selection::All, &sheet_names, 0).unwrap(), sheet_names);
+    }
+}